@@ -1,9 +1,34 @@
+use std::sync::OnceLock;
+
 use rayon::prelude::*;
 
 use crate::backends::cpu::buf::CpuTensorBuf;
 use crate::error::Result;
 use crate::tensor::TensorStrider;
 
+/// A pluggable backend for the strided `(b, m, k) @ (b, k) -> (b, m)` matmul-vec,
+/// the single most expensive per-token operation.
+///
+/// [`CpuBackend`] (the Rayon loop) is the default and the fallback: a device
+/// backend, compiled in behind the `cuda`/`gpu` feature, only takes over when
+/// the batch dimension and `k` are large enough to amortize the dispatch. This
+/// keeps the public [`batch_matmul_vec`] API unchanged while making the hot
+/// kernel swappable.
+pub trait MatmulVecBackend {
+    fn batch_matmul_vec<'a>(
+        a: &CpuTensorBuf<'a>,
+        b: &CpuTensorBuf<'a>,
+        c: &mut CpuTensorBuf<'a>,
+        strider1: &TensorStrider,
+        strider2: &TensorStrider,
+    ) -> Result<()>;
+}
+
+/// Minimum batch size (`b`) before a device backend is considered.
+pub const MATMUL_VEC_OFFLOAD_MIN_BATCH: usize = 8;
+/// Minimum inner dimension (`k`) before a device backend is considered.
+pub const MATMUL_VEC_OFFLOAD_MIN_K: usize = 512;
+
 // (b, m, k) @ (b, k, ) -> (b, m, )
 // a is allowed to be not contiguous, but not quantized
 pub fn batch_matmul_vec<'a>(
@@ -13,147 +38,918 @@ pub fn batch_matmul_vec<'a>(
     strider1: &TensorStrider,
     strider2: &TensorStrider,
 ) -> Result<()> {
-    assert!(strider1.shape().len() == 3);
-    assert!(strider2.shape().len() == 2);
-    assert!(strider1.shape()[0] == strider2.shape()[0]);
-    assert!(strider1.shape()[2] == strider2.shape()[1]);
-    assert!(strider2.is_contiguous());
-
-    let bufa = a.as_f32_ref();
-    let bufb = b.as_f32_ref();
-    let bufc = c.as_f32_mut();
-
-    let m = strider1.shape()[1];
-    let k = strider1.shape()[2];
-    let bi_stride = strider1.strides()[0];
-    let mi_stride = strider1.strides()[1];
-    let ki_stride = strider1.strides()[2];
+    // Offload to the feature-selected device backend for large shapes; the CPU
+    // path remains the default and fallback for small shapes and when no device
+    // backend is compiled in.
+    #[cfg(any(feature = "cuda", feature = "gpu"))]
+    {
+        let bn = strider1.shape()[0];
+        let k = strider1.shape()[2];
+        if bn >= MATMUL_VEC_OFFLOAD_MIN_BATCH && k >= MATMUL_VEC_OFFLOAD_MIN_K {
+            return gpu::GpuBackend::batch_matmul_vec(a, b, c, strider1, strider2);
+        }
+    }
+    CpuBackend::batch_matmul_vec(a, b, c, strider1, strider2)
+}
+
+/// The default Rayon CPU backend.
+pub struct CpuBackend;
+
+impl MatmulVecBackend for CpuBackend {
+    fn batch_matmul_vec<'a>(
+        a: &CpuTensorBuf<'a>,
+        b: &CpuTensorBuf<'a>,
+        c: &mut CpuTensorBuf<'a>,
+        strider1: &TensorStrider,
+        strider2: &TensorStrider,
+    ) -> Result<()> {
+        assert!(strider1.shape().len() == 3);
+        assert!(strider2.shape().len() == 2);
+        assert!(strider1.shape()[0] == strider2.shape()[0]);
+        assert!(strider1.shape()[2] == strider2.shape()[1]);
+        assert!(strider2.is_contiguous());
 
+        let bufb = b.as_f32_ref();
+        let bufc = c.as_f32_mut();
+
+        let m = strider1.shape()[1];
+        let k = strider1.shape()[2];
+        let bi_stride = strider1.strides()[0];
+        let mi_stride = strider1.strides()[1];
+        let ki_stride = strider1.strides()[2];
+
+        // Dispatch on the storage dtype of the weight matrix `a`. f16/bf16 weights
+        // stay in their compact form through the hot loop; only the activation
+        // vector `b` is held as f32, with the weight lanes converted inline. This
+        // halves the memory traffic on the dominant matmul-vec path compared with
+        // widening `a` to f32 up front.
+        match a {
+            CpuTensorBuf::F16(bufa) => {
+                let bufa = bufa.as_ref();
+                matmul_vec_rows(bufc, m, |bi, mi| {
+                    let base = bi * bi_stride + mi * mi_stride;
+                    dot_product_f16(bufa, base, ki_stride, k, &bufb[bi * k..(bi + 1) * k])
+                });
+            }
+            CpuTensorBuf::BF16(bufa) => {
+                let bufa = bufa.as_ref();
+                matmul_vec_rows(bufc, m, |bi, mi| {
+                    let base = bi * bi_stride + mi * mi_stride;
+                    dot_product_bf16(bufa, base, ki_stride, k, &bufb[bi * k..(bi + 1) * k])
+                });
+            }
+            _ => {
+                let bufa = a.as_f32_ref();
+                matmul_vec_rows(bufc, m, |bi, mi| {
+                    let base = bi * bi_stride + mi * mi_stride;
+                    dot_product_f32(bufa, base, ki_stride, k, &bufb[bi * k..(bi + 1) * k])
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The feature-gated device backend. The actual kernel lives in the device
+/// crate; until one is wired in, the integration point falls back to the CPU
+/// implementation so the offload hook is testable without hardware.
+#[cfg(any(feature = "cuda", feature = "gpu"))]
+mod gpu {
+    use super::*;
+
+    pub struct GpuBackend;
+
+    impl MatmulVecBackend for GpuBackend {
+        fn batch_matmul_vec<'a>(
+            a: &CpuTensorBuf<'a>,
+            b: &CpuTensorBuf<'a>,
+            c: &mut CpuTensorBuf<'a>,
+            strider1: &TensorStrider,
+            strider2: &TensorStrider,
+        ) -> Result<()> {
+            // TODO: dispatch the strided (b,m,k)@(b,k)->(b,m) op to a device
+            // kernel. Fall back to the CPU backend until that lands.
+            CpuBackend::batch_matmul_vec(a, b, c, strider1, strider2)
+        }
+    }
+}
+
+/// Drive the per-row dot product in parallel over the `(b, m)` output.
+///
+/// `dot` receives the batch index `bi` and the row index `mi`, returning the
+/// output cell. Factoring this out keeps the dtype dispatch in
+/// [`batch_matmul_vec`] from repeating the Rayon loop three times.
+fn matmul_vec_rows<F>(bufc: &mut [f32], m: usize, dot: F)
+where
+    F: Fn(usize, usize) -> f32 + Sync,
+{
     bufc.par_iter_mut().enumerate().for_each(|(i, bufcp)| {
         let mi = i % m;
         let bi = (i - mi) / m;
-        *bufcp = dot_product_f32(
-            bufa,
-            bi * bi_stride + mi * mi_stride,
-            ki_stride,
-            k,
-            &bufb[bi * k..(bi + 1) * k],
-        );
+        *bufcp = dot(bi, mi);
     });
+}
+
+/// A single SIMD f32 kernel, following candle's `Cpu` trait pattern.
+///
+/// `ARR` is the number of accumulator registers unrolled per loop iteration; an
+/// implementation therefore consumes `STEP = ARR * EPR` elements per iteration.
+/// The accumulator "array" type the request talks about is simply
+/// `[Self::Unit; ARR]`, which keeps it nameable and indexable inside the generic
+/// [`vec_dot`] without pulling in `generic_const_exprs`. Adding a new kernel
+/// (SSE, AVX-512, …) is then one small `impl` rather than a copy-pasted
+/// function.
+trait SimdF32<const ARR: usize> {
+    /// One SIMD register holding `EPR` f32 lanes.
+    type Unit: Copy;
+    /// Elements per register.
+    const EPR: usize;
+    /// Elements processed per loop iteration (`ARR * EPR`).
+    const STEP: usize;
+
+    /// A zeroed accumulator register.
+    unsafe fn zero() -> Self::Unit;
+    /// Gather `EPR` lanes of the strided `a` column starting at `base`.
+    unsafe fn gather(base: *const f32, stride: usize) -> Self::Unit;
+    /// Load `EPR` contiguous lanes of the activation vector `b`.
+    unsafe fn load(ptr: *const f32) -> Self::Unit;
+    /// Multiply-add: `a * b + c` (fused only where the kernel has FMA).
+    unsafe fn fma(a: Self::Unit, b: Self::Unit, c: Self::Unit) -> Self::Unit;
+    /// Horizontally reduce the `ARR` accumulators to a scalar.
+    unsafe fn reduce(acc: [Self::Unit; ARR]) -> f32;
+}
+
+/// Strided dot product of the `a` column against `b`, generic over the kernel.
+///
+/// Gathers the strided `a` lanes, runs the unrolled `STEP`-wide accumulate loop,
+/// reduces the accumulators, then handles the scalar tail.
+#[inline]
+unsafe fn vec_dot<const ARR: usize, S: SimdF32<ARR>>(
+    a: &[f32],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    let a_ptr = a.as_ptr().add(a_base);
+    let b_ptr = b.as_ptr();
 
-    Ok(())
+    let mut acc = [S::zero(); ARR];
+    let k_rounded = k - k % S::STEP;
+    let mut ki = 0;
+    while ki < k_rounded {
+        for j in 0..ARR {
+            let off = ki + j * S::EPR;
+            let av = S::gather(a_ptr.add(off * a_stride), a_stride);
+            let bv = S::load(b_ptr.add(off));
+            acc[j] = S::fma(av, bv, acc[j]);
+        }
+        ki += S::STEP;
+    }
+
+    let mut sum = S::reduce(acc);
+    while ki < k {
+        sum += a[a_base + ki * a_stride] * b[ki];
+        ki += 1;
+    }
+    sum
 }
 
-/// TODO: we need to find a better way to organize these functions with different arch and features.
+/// The signature shared by every `dot_product_f32` kernel. The kernels are
+/// `#[target_feature]`-annotated, so the pointer is `unsafe`: it may only be
+/// called once the corresponding CPU feature has been detected at runtime.
+type DotProductF32Fn = unsafe fn(&[f32], usize, usize, usize, &[f32]) -> f32;
+
+/// Pick the best `dot_product_f32` kernel for the host CPU and cache it.
+///
+/// A binary distributed on crates.io is built for a generic baseline (no
+/// `avx2`/`fma` in `target-feature`), so the old `#[cfg(target_feature)]`
+/// dispatch compiled down to the scalar fallback even on capable machines.
+/// We instead probe the CPU once via `is_*_feature_detected!` and route every
+/// call through the cached pointer, modeled on curve25519-dalek's backend
+/// autodetection. Each kernel is a distinct `#[target_feature]` function so the
+/// compiler actually emits the wide instructions regardless of the build-time
+/// baseline.
 pub fn dot_product_f32(a: &[f32], a_base: usize, a_stride: usize, k: usize, b: &[f32]) -> f32 {
+    static IMPL: OnceLock<DotProductF32Fn> = OnceLock::new();
+    let f = *IMPL.get_or_init(detect_dot_product_f32);
+    // SAFETY: `detect_dot_product_f32` only returns a kernel whose required
+    // feature was reported present by `is_*_feature_detected!`.
+    unsafe { f(a, a_base, a_stride, k, b) }
+}
+
+fn detect_dot_product_f32() -> DotProductF32Fn {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx512f") {
+            return dot_product_f32_avx512;
+        }
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            return dot_product_f32_avx2;
+        }
+        if is_x86_feature_detected!("sse2") {
+            return dot_product_f32_sse2;
+        }
+    }
     #[cfg(target_arch = "aarch64")]
     {
-        dot_product_f32_simd(a, a_base, a_stride, k, b)
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return dot_product_f32_neon;
+        }
     }
-    #[cfg(target_arch = "x86_64")]
-    #[cfg(target_feature = "avx2")]
+    // On targets without a hand-written intrinsic kernel (wasm32, riscv64,
+    // ppc64le, …) use the portable `std::simd` kernel when it is compiled in;
+    // otherwise fall back to the 4-wide scalar loop.
+    #[cfg(feature = "portable-simd")]
     {
-        dot_product_f32_simd(a, a_base, a_stride, k, b)
+        return dot_product_f32_portable;
     }
-    #[cfg(not(any(
-        target_arch = "aarch64",
-        all(target_arch = "x86_64", target_feature = "avx2")
-    )))]
+    #[cfg(not(feature = "portable-simd"))]
     {
-        dot_product_f32_fallback(a, a_base, a_stride, k, b)
+        dot_product_f32_fallback
     }
 }
 
-#[cfg(not(any(
-    target_arch = "aarch64",
-    all(target_arch = "x86_64", target_feature = "avx2")
-)))]
-fn dot_product_f32_fallback(a: &[f32], a_base: usize, a_stride: usize, k: usize, b: &[f32]) -> f32 {
-    let mut sum = 0.0;
-    let k_rounded = k - k % 4;
-    for ki in (0..k_rounded).step_by(4) {
-        sum += a[a_base + ki * a_stride] * b[ki];
-        sum += a[a_base + (ki + 1) * a_stride] * b[ki + 1];
-        sum += a[a_base + (ki + 2) * a_stride] * b[ki + 2];
-        sum += a[a_base + (ki + 3) * a_stride] * b[ki + 3];
+/// Scalar 4-wide unrolled fallback for targets without a vectorized kernel.
+///
+/// Only referenced when the `portable-simd` kernel is not compiled in; with that
+/// feature enabled [`dot_product_f32_portable`] is the non-intrinsic baseline on
+/// every target, so gate this out to keep it from tripping `dead_code`.
+#[cfg(not(feature = "portable-simd"))]
+struct FallbackF32;
+
+#[cfg(not(feature = "portable-simd"))]
+impl SimdF32<4> for FallbackF32 {
+    type Unit = f32;
+    const EPR: usize = 1;
+    const STEP: usize = 4;
+
+    #[inline(always)]
+    unsafe fn zero() -> f32 {
+        0.0
     }
-    for ki in (k_rounded..k).step_by(1) {
-        sum += a[a_base + ki * a_stride] * b[ki];
+    #[inline(always)]
+    unsafe fn gather(base: *const f32, _stride: usize) -> f32 {
+        *base
+    }
+    #[inline(always)]
+    unsafe fn load(ptr: *const f32) -> f32 {
+        *ptr
+    }
+    #[inline(always)]
+    unsafe fn fma(a: f32, b: f32, c: f32) -> f32 {
+        a * b + c
+    }
+    #[inline(always)]
+    unsafe fn reduce(acc: [f32; 4]) -> f32 {
+        acc[0] + acc[1] + acc[2] + acc[3]
+    }
+}
+
+#[cfg(not(feature = "portable-simd"))]
+unsafe fn dot_product_f32_fallback(
+    a: &[f32],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    vec_dot::<4, FallbackF32>(a, a_base, a_stride, k, b)
+}
+
+/// Portable 8-wide kernel built on `core::simd`, gated behind the
+/// `portable-simd` feature (which also requires `#![feature(portable_simd)]`
+/// on nightly, enabled in the crate root). It gives every target without a
+/// hand-written intrinsic kernel — wasm32 `simd128`, riscv64 `V`, ppc64le — a
+/// single maintained vectorized codepath, and doubles as a reference
+/// implementation to test the intrinsic kernels against.
+#[cfg(feature = "portable-simd")]
+struct PortableF32;
+
+#[cfg(feature = "portable-simd")]
+impl SimdF32<2> for PortableF32 {
+    type Unit = std::simd::Simd<f32, 8>;
+    const EPR: usize = 8;
+    const STEP: usize = 16;
+
+    #[inline(always)]
+    unsafe fn zero() -> Self::Unit {
+        std::simd::Simd::splat(0.0)
+    }
+    #[inline(always)]
+    unsafe fn gather(base: *const f32, stride: usize) -> Self::Unit {
+        std::simd::Simd::from_array([
+            *base,
+            *base.add(stride),
+            *base.add(2 * stride),
+            *base.add(3 * stride),
+            *base.add(4 * stride),
+            *base.add(5 * stride),
+            *base.add(6 * stride),
+            *base.add(7 * stride),
+        ])
+    }
+    #[inline(always)]
+    unsafe fn load(ptr: *const f32) -> Self::Unit {
+        std::simd::Simd::from_slice(std::slice::from_raw_parts(ptr, 8))
+    }
+    #[inline(always)]
+    unsafe fn fma(a: Self::Unit, b: Self::Unit, c: Self::Unit) -> Self::Unit {
+        use std::simd::StdFloat;
+        a.mul_add(b, c)
+    }
+    #[inline(always)]
+    unsafe fn reduce(acc: [Self::Unit; 2]) -> f32 {
+        use std::simd::num::SimdFloat;
+        acc[0].reduce_sum() + acc[1].reduce_sum()
+    }
+}
+
+#[cfg(feature = "portable-simd")]
+unsafe fn dot_product_f32_portable(
+    a: &[f32],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    vec_dot::<2, PortableF32>(a, a_base, a_stride, k, b)
+}
+
+#[cfg(target_arch = "aarch64")]
+struct NeonF32;
+
+#[cfg(target_arch = "aarch64")]
+impl SimdF32<2> for NeonF32 {
+    type Unit = std::arch::aarch64::float32x4_t;
+    const EPR: usize = 4;
+    const STEP: usize = 8;
+
+    #[inline(always)]
+    #[target_feature(enable = "neon")]
+    unsafe fn zero() -> Self::Unit {
+        std::arch::aarch64::vdupq_n_f32(0.0)
+    }
+    #[inline(always)]
+    #[target_feature(enable = "neon")]
+    unsafe fn gather(base: *const f32, stride: usize) -> Self::Unit {
+        let tmp = [
+            *base,
+            *base.add(stride),
+            *base.add(2 * stride),
+            *base.add(3 * stride),
+        ];
+        std::arch::aarch64::vld1q_f32(tmp.as_ptr())
+    }
+    #[inline(always)]
+    #[target_feature(enable = "neon")]
+    unsafe fn load(ptr: *const f32) -> Self::Unit {
+        std::arch::aarch64::vld1q_f32(ptr)
+    }
+    #[inline(always)]
+    #[target_feature(enable = "neon")]
+    unsafe fn fma(a: Self::Unit, b: Self::Unit, c: Self::Unit) -> Self::Unit {
+        std::arch::aarch64::vfmaq_f32(c, a, b)
+    }
+    #[inline(always)]
+    #[target_feature(enable = "neon")]
+    unsafe fn reduce(acc: [Self::Unit; 2]) -> f32 {
+        std::arch::aarch64::vaddvq_f32(acc[0]) + std::arch::aarch64::vaddvq_f32(acc[1])
     }
-    sum
 }
 
 #[cfg(target_arch = "aarch64")]
-fn dot_product_f32_simd(a: &[f32], a_base: usize, a_stride: usize, k: usize, b: &[f32]) -> f32 {
-    use std::arch::aarch64;
-
-    unsafe {
-        let a_ptr = a.as_ptr().add(a_base);
-
-        let mut sumv0 = aarch64::vdupq_n_f32(0.0);
-        let mut sumv1 = aarch64::vdupq_n_f32(0.0);
-        let k_rounded = k - k % 8;
-        for ki in (0..k_rounded).step_by(8) {
-            let av_tmp = [
-                *a_ptr.add(ki * a_stride),
-                *a_ptr.add((ki + 1) * a_stride),
-                *a_ptr.add((ki + 2) * a_stride),
-                *a_ptr.add((ki + 3) * a_stride),
-                *a_ptr.add((ki + 4) * a_stride),
-                *a_ptr.add((ki + 5) * a_stride),
-                *a_ptr.add((ki + 6) * a_stride),
-                *a_ptr.add((ki + 7) * a_stride),
-            ];
-            let av0 = aarch64::vld1q_f32(av_tmp.as_ptr());
-            let bv0 = aarch64::vld1q_f32(b.as_ptr().add(ki));
-            let av1 = aarch64::vld1q_f32(av_tmp.as_ptr().add(4));
-            let bv1 = aarch64::vld1q_f32(b.as_ptr().add(ki + 4));
-            sumv0 = aarch64::vfmaq_f32(sumv0, av0, bv0);
-            sumv1 = aarch64::vfmaq_f32(sumv1, av1, bv1);
-        }
-
-        let mut sum = aarch64::vaddvq_f32(sumv0) + aarch64::vaddvq_f32(sumv1);
-        for ki in k_rounded..k {
-            sum += a[a_base + ki * a_stride] * b[ki];
-        }
-        sum
+#[target_feature(enable = "neon")]
+unsafe fn dot_product_f32_neon(
+    a: &[f32],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    vec_dot::<2, NeonF32>(a, a_base, a_stride, k, b)
+}
+
+#[cfg(target_arch = "x86_64")]
+struct Avx2F32;
+
+#[cfg(target_arch = "x86_64")]
+impl SimdF32<1> for Avx2F32 {
+    type Unit = std::arch::x86_64::__m256;
+    const EPR: usize = 8;
+    const STEP: usize = 8;
+
+    #[inline(always)]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn zero() -> Self::Unit {
+        std::arch::x86_64::_mm256_setzero_ps()
+    }
+    #[inline(always)]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn gather(base: *const f32, stride: usize) -> Self::Unit {
+        use std::arch::x86_64::*;
+        if stride == 1 {
+            // Contiguous column: a single wide load beats a hardware gather.
+            _mm256_loadu_ps(base)
+        } else {
+            // Strided column (the transposed-view case batch_matmul_vec
+            // supports): pull the eight lanes in one `vgatherdps` instead of
+            // eight scalar loads into a stack temp. Offsets are in element
+            // units scaled by 4 bytes, i.e. `{0, s, 2s, … 7s}`.
+            let s = stride as i32;
+            let offsets = _mm256_setr_epi32(0, s, 2 * s, 3 * s, 4 * s, 5 * s, 6 * s, 7 * s);
+            _mm256_i32gather_ps::<4>(base, offsets)
+        }
+    }
+    #[inline(always)]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn load(ptr: *const f32) -> Self::Unit {
+        std::arch::x86_64::_mm256_loadu_ps(ptr)
+    }
+    #[inline(always)]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn fma(a: Self::Unit, b: Self::Unit, c: Self::Unit) -> Self::Unit {
+        std::arch::x86_64::_mm256_fmadd_ps(a, b, c)
     }
+    #[inline(always)]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn reduce(acc: [Self::Unit; 1]) -> f32 {
+        let mut sum_arr = [0.0_f32; 8];
+        std::arch::x86_64::_mm256_storeu_ps(sum_arr.as_mut_ptr(), acc[0]);
+        sum_arr.iter().sum::<f32>()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn dot_product_f32_avx2(
+    a: &[f32],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    vec_dot::<1, Avx2F32>(a, a_base, a_stride, k, b)
 }
 
 #[cfg(target_arch = "x86_64")]
-#[cfg(target_feature = "avx2")]
-fn dot_product_f32_simd(a: &[f32], a_base: usize, a_stride: usize, k: usize, b: &[f32]) -> f32 {
+struct Avx512F32;
+
+#[cfg(target_arch = "x86_64")]
+impl SimdF32<1> for Avx512F32 {
+    type Unit = std::arch::x86_64::__m512;
+    const EPR: usize = 16;
+    const STEP: usize = 16;
+
+    #[inline(always)]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn zero() -> Self::Unit {
+        std::arch::x86_64::_mm512_setzero_ps()
+    }
+    #[inline(always)]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn gather(base: *const f32, stride: usize) -> Self::Unit {
+        let mut tmp = [0.0_f32; 16];
+        for (i, slot) in tmp.iter_mut().enumerate() {
+            *slot = *base.add(i * stride);
+        }
+        std::arch::x86_64::_mm512_loadu_ps(tmp.as_ptr())
+    }
+    #[inline(always)]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn load(ptr: *const f32) -> Self::Unit {
+        std::arch::x86_64::_mm512_loadu_ps(ptr)
+    }
+    #[inline(always)]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn fma(a: Self::Unit, b: Self::Unit, c: Self::Unit) -> Self::Unit {
+        std::arch::x86_64::_mm512_fmadd_ps(a, b, c)
+    }
+    #[inline(always)]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn reduce(acc: [Self::Unit; 1]) -> f32 {
+        std::arch::x86_64::_mm512_reduce_add_ps(acc[0])
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+unsafe fn dot_product_f32_avx512(
+    a: &[f32],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    vec_dot::<1, Avx512F32>(a, a_base, a_stride, k, b)
+}
+
+#[cfg(target_arch = "x86_64")]
+struct Sse2F32;
+
+#[cfg(target_arch = "x86_64")]
+impl SimdF32<1> for Sse2F32 {
+    type Unit = std::arch::x86_64::__m128;
+    const EPR: usize = 4;
+    const STEP: usize = 4;
+
+    #[inline(always)]
+    #[target_feature(enable = "sse2")]
+    unsafe fn zero() -> Self::Unit {
+        std::arch::x86_64::_mm_setzero_ps()
+    }
+    #[inline(always)]
+    #[target_feature(enable = "sse2")]
+    unsafe fn gather(base: *const f32, stride: usize) -> Self::Unit {
+        std::arch::x86_64::_mm_set_ps(
+            *base.add(3 * stride),
+            *base.add(2 * stride),
+            *base.add(stride),
+            *base,
+        )
+    }
+    #[inline(always)]
+    #[target_feature(enable = "sse2")]
+    unsafe fn load(ptr: *const f32) -> Self::Unit {
+        std::arch::x86_64::_mm_loadu_ps(ptr)
+    }
+    #[inline(always)]
+    #[target_feature(enable = "sse2")]
+    unsafe fn fma(a: Self::Unit, b: Self::Unit, c: Self::Unit) -> Self::Unit {
+        // sse2 has no FMA; emulate with mul + add.
+        std::arch::x86_64::_mm_add_ps(c, std::arch::x86_64::_mm_mul_ps(a, b))
+    }
+    #[inline(always)]
+    #[target_feature(enable = "sse2")]
+    unsafe fn reduce(acc: [Self::Unit; 1]) -> f32 {
+        let mut sum_arr = [0.0_f32; 4];
+        std::arch::x86_64::_mm_storeu_ps(sum_arr.as_mut_ptr(), acc[0]);
+        sum_arr.iter().sum::<f32>()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn dot_product_f32_sse2(
+    a: &[f32],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    vec_dot::<1, Sse2F32>(a, a_base, a_stride, k, b)
+}
+
+/// A half-precision storage type that can participate in an f32 dot product.
+///
+/// Mirrors the f32 [`SimdF32`] trait but for the `CpuF16` family candle keeps
+/// separate: the weight lanes live as `half::f16`/`half::bf16` and are widened
+/// to f32 only as they are consumed, so the compact storage survives the hot
+/// loop. `widen8_avx` widens eight contiguous lanes (read as raw bits) into an
+/// AVX register in one shot — `_mm256_cvtph_ps` for f16, a shift-left for bf16.
+///
+/// On aarch64, f16 additionally has a native `float16x8_t` + `vfmaq_f16` kernel
+/// ([`vec_dot_f16_neon`]), gated behind the `fp16` feature since it relies on the
+/// still-unstable `stdarch_neon_f16` intrinsics; bf16 stays on the per-element
+/// widening scalar fallback there, matching the request's fp16-only ARM scope.
+trait CpuF16: Copy {
+    /// Widen a single lane to f32 (used by the scalar fallback and the tail).
+    fn to_f32(self) -> f32;
+    /// The raw 16-bit storage bits, for gathering strided lanes cheaply.
+    fn to_bits(self) -> u16;
+    /// Widen eight contiguous lanes (as raw bits) to an f32 AVX register.
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn widen8_avx(bits: *const u16) -> std::arch::x86_64::__m256;
+}
+
+impl CpuF16 for half::f16 {
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        self.to_f32()
+    }
+    #[inline(always)]
+    fn to_bits(self) -> u16 {
+        half::f16::to_bits(self)
+    }
+    #[cfg(target_arch = "x86_64")]
+    #[inline(always)]
+    #[target_feature(enable = "f16c")]
+    unsafe fn widen8_avx(bits: *const u16) -> std::arch::x86_64::__m256 {
+        use std::arch::x86_64::*;
+        _mm256_cvtph_ps(_mm_loadu_si128(bits as *const __m128i))
+    }
+}
+
+impl CpuF16 for half::bf16 {
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        self.to_f32()
+    }
+    #[inline(always)]
+    fn to_bits(self) -> u16 {
+        half::bf16::to_bits(self)
+    }
+    #[cfg(target_arch = "x86_64")]
+    #[inline(always)]
+    #[target_feature(enable = "avx2")]
+    unsafe fn widen8_avx(bits: *const u16) -> std::arch::x86_64::__m256 {
+        use std::arch::x86_64::*;
+        // bf16 -> f32 is just the high 16 bits: zero-extend then shift left 16.
+        let raw = _mm_loadu_si128(bits as *const __m128i);
+        let widened = _mm256_slli_epi32(_mm256_cvtepu16_epi32(raw), 16);
+        _mm256_castsi256_ps(widened)
+    }
+}
+
+/// Scalar fallback shared by f16 and bf16: widen each lane on demand.
+unsafe fn vec_dot_half_scalar<H: CpuF16>(
+    a: &[H],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    let mut sum = 0.0;
+    for ki in 0..k {
+        sum += a[a_base + ki * a_stride].to_f32() * b[ki];
+    }
+    sum
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn vec_dot_half_avx2<H: CpuF16>(
+    a: &[H],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
     use std::arch::x86_64::*;
 
-    unsafe {
-        let a_ptr = a.as_ptr().add(a_base);
+    let a_ptr = a.as_ptr().add(a_base);
+    let mut sumv = _mm256_setzero_ps();
+    let k_rounded = k - k % 8;
+    let mut ki = 0;
+    while ki < k_rounded {
+        // Gather the strided weight lanes as raw bits, then widen in one shot.
+        let mut bits = [0u16; 8];
+        for (i, slot) in bits.iter_mut().enumerate() {
+            *slot = (*a_ptr.add((ki + i) * a_stride)).to_bits();
+        }
+        let av = H::widen8_avx(bits.as_ptr());
+        let bv = _mm256_loadu_ps(b.as_ptr().add(ki));
+        sumv = _mm256_fmadd_ps(av, bv, sumv);
+        ki += 8;
+    }
+
+    let mut sum_arr = [0.0_f32; 8];
+    _mm256_storeu_ps(sum_arr.as_mut_ptr(), sumv);
+    let mut sum = sum_arr.iter().sum::<f32>();
+    while ki < k {
+        sum += (*a_ptr.add(ki * a_stride)).to_f32() * b[ki];
+        ki += 1;
+    }
+    sum
+}
+
+/// Native aarch64 half-precision kernel, gated behind the `fp16` feature (which
+/// also requires `#![feature(stdarch_neon_f16)]` on nightly, enabled in the
+/// crate root). Weights stay in `half::f16` and are multiply-accumulated in
+/// `float16x8_t` via `vfmaq_f16` at an 8-wide step; the f32 activation lanes are
+/// narrowed to f16 inline, and the accumulator is widened back to f32 only for
+/// the final reduction and the scalar tail.
+#[cfg(all(target_arch = "aarch64", feature = "fp16"))]
+#[inline]
+#[target_feature(enable = "neon,fp16")]
+unsafe fn vec_dot_f16_neon(
+    a: &[half::f16],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    use std::arch::aarch64::*;
+
+    let a_ptr = a.as_ptr().add(a_base);
+    let mut acc = vdupq_n_f16(0.0);
+    let k_rounded = k - k % 8;
+    let mut ki = 0;
+    while ki < k_rounded {
+        // Gather the eight strided weight lanes, then load them as one register.
+        let mut tmp = [half::f16::ZERO; 8];
+        for (i, slot) in tmp.iter_mut().enumerate() {
+            *slot = *a_ptr.add((ki + i) * a_stride);
+        }
+        let av = vld1q_f16(tmp.as_ptr() as *const f16);
+        // Narrow the eight f32 activations to f16 to match the weight register.
+        let b_lo = vld1q_f32(b.as_ptr().add(ki));
+        let b_hi = vld1q_f32(b.as_ptr().add(ki + 4));
+        let bv = vcombine_f16(vcvt_f16_f32(b_lo), vcvt_f16_f32(b_hi));
+        acc = vfmaq_f16(acc, av, bv);
+        ki += 8;
+    }
+
+    // Widen the f16 accumulator to f32 for a precise horizontal sum.
+    let lo = vcvt_f32_f16(vget_low_f16(acc));
+    let hi = vcvt_f32_f16(vget_high_f16(acc));
+    let mut sum = vaddvq_f32(lo) + vaddvq_f32(hi);
+    while ki < k {
+        sum += (*a_ptr.add(ki * a_stride)).to_f32() * b[ki];
+        ki += 1;
+    }
+    sum
+}
+
+#[cfg(all(target_arch = "aarch64", feature = "fp16"))]
+#[target_feature(enable = "neon,fp16")]
+unsafe fn dot_product_f16_neon(
+    a: &[half::f16],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    vec_dot_f16_neon(a, a_base, a_stride, k, b)
+}
 
-        let mut sumv = _mm256_setzero_ps();
-        let k_rounded_down = k - k % 8; // Round down to the nearest multiple of 8
+type DotProductF16Fn = unsafe fn(&[half::f16], usize, usize, usize, &[f32]) -> f32;
+type DotProductBF16Fn = unsafe fn(&[half::bf16], usize, usize, usize, &[f32]) -> f32;
 
-        for ki in (0..k_rounded_down).step_by(8) {
-            let mut av_tmp = [0.0_f32; 8];
-            // Load elements from 'a' with stride
-            for i in 0..8 {
-                av_tmp[i] = *a_ptr.add(ki * a_stride + i * a_stride);
+/// Dot product of a strided f16 weight column against an f32 activation vector.
+pub fn dot_product_f16(
+    a: &[half::f16],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    static IMPL: OnceLock<DotProductF16Fn> = OnceLock::new();
+    let f = *IMPL.get_or_init(|| {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2")
+                && is_x86_feature_detected!("fma")
+                && is_x86_feature_detected!("f16c")
+            {
+                return dot_product_f16_avx2 as DotProductF16Fn;
+            }
+        }
+        #[cfg(all(target_arch = "aarch64", feature = "fp16"))]
+        {
+            if std::arch::is_aarch64_feature_detected!("fp16") {
+                return dot_product_f16_neon as DotProductF16Fn;
             }
-            let av = _mm256_loadu_ps(av_tmp.as_ptr());
-            let bv = _mm256_loadu_ps(b.as_ptr().add(ki));
-            // Fused multiply-add operation: sumv += av * bv
-            sumv = _mm256_fmadd_ps(av, bv, sumv);
         }
+        dot_product_f16_scalar
+    });
+    // SAFETY: the detected kernel's features were confirmed present above.
+    unsafe { f(a, a_base, a_stride, k, b) }
+}
 
-        // Horizontal sum of the vector elements
-        let mut sum_arr = [0.0_f32; 8];
-        _mm256_storeu_ps(sum_arr.as_mut_ptr(), sumv);
-        let partial_sum = sum_arr.iter().sum::<f32>();
+unsafe fn dot_product_f16_scalar(
+    a: &[half::f16],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    vec_dot_half_scalar(a, a_base, a_stride, k, b)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,fma,f16c")]
+unsafe fn dot_product_f16_avx2(
+    a: &[half::f16],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    vec_dot_half_avx2(a, a_base, a_stride, k, b)
+}
+
+/// Dot product of a strided bf16 weight column against an f32 activation vector.
+pub fn dot_product_bf16(
+    a: &[half::bf16],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    static IMPL: OnceLock<DotProductBF16Fn> = OnceLock::new();
+    let f = *IMPL.get_or_init(|| {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+                return dot_product_bf16_avx2 as DotProductBF16Fn;
+            }
+        }
+        dot_product_bf16_scalar
+    });
+    // SAFETY: the detected kernel's features were confirmed present above.
+    unsafe { f(a, a_base, a_stride, k, b) }
+}
+
+unsafe fn dot_product_bf16_scalar(
+    a: &[half::bf16],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    vec_dot_half_scalar(a, a_base, a_stride, k, b)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn dot_product_bf16_avx2(
+    a: &[half::bf16],
+    a_base: usize,
+    a_stride: usize,
+    k: usize,
+    b: &[f32],
+) -> f32 {
+    vec_dot_half_avx2(a, a_base, a_stride, k, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // Scalar computation for the remaining elements
-        let mut scalar_sum = 0.0;
-        for ki in k_rounded_down..k {
-            scalar_sum += a[a_base + ki * a_stride] * b[ki];
+    /// Plain scalar reference the dispatched kernels must agree with.
+    fn oracle(a: &[f32], a_base: usize, a_stride: usize, k: usize, b: &[f32]) -> f32 {
+        (0..k).map(|ki| a[a_base + ki * a_stride] * b[ki]).sum()
+    }
+
+    /// Lay out `k` weight lanes at `a_stride` starting from `a_base`, filling the
+    /// gaps so a stride-unaware kernel that reads the wrong lanes would diverge.
+    fn strided(a_base: usize, a_stride: usize, k: usize) -> Vec<f32> {
+        let len = a_base + (k.max(1) - 1) * a_stride + 1;
+        (0..len).map(|i| ((i % 17) as f32) * 0.5 - 3.0).collect()
+    }
+
+    fn activation(k: usize) -> Vec<f32> {
+        (0..k).map(|i| ((i % 13) as f32) * 0.25 - 1.5).collect()
+    }
+
+    #[test]
+    fn f32_kernel_matches_oracle() {
+        // Cover a_stride == 1 (contiguous load path), a_stride != 1 (gather path),
+        // and k that is not a multiple of any kernel's STEP (scalar tail).
+        for &a_stride in &[1usize, 3, 8] {
+            for &k in &[0usize, 1, 7, 8, 15, 16, 33, 64, 129] {
+                let a_base = 5;
+                let a = strided(a_base, a_stride, k);
+                let b = activation(k);
+                let got = dot_product_f32(&a, a_base, a_stride, k, &b);
+                let want = oracle(&a, a_base, a_stride, k, &b);
+                assert!(
+                    (got - want).abs() <= 1e-3 * want.abs().max(1.0),
+                    "f32 stride={a_stride} k={k}: got {got} want {want}"
+                );
+            }
         }
+    }
+
+    #[test]
+    fn half_kernels_match_oracle() {
+        for &a_stride in &[1usize, 3, 8] {
+            for &k in &[0usize, 1, 7, 8, 15, 16, 33, 64] {
+                let a_base = 2;
+                let a = strided(a_base, a_stride, k);
+                let b = activation(k);
 
-        partial_sum + scalar_sum
+                let a16: Vec<half::f16> = a.iter().map(|&x| half::f16::from_f32(x)).collect();
+                let want16 = oracle(
+                    &a16.iter().map(|x| x.to_f32()).collect::<Vec<_>>(),
+                    a_base,
+                    a_stride,
+                    k,
+                    &b,
+                );
+                let got16 = dot_product_f16(&a16, a_base, a_stride, k, &b);
+                assert!(
+                    (got16 - want16).abs() <= 1e-2 * want16.abs().max(1.0),
+                    "f16 stride={a_stride} k={k}: got {got16} want {want16}"
+                );
+
+                let abf: Vec<half::bf16> = a.iter().map(|&x| half::bf16::from_f32(x)).collect();
+                let wantbf = oracle(
+                    &abf.iter().map(|x| x.to_f32()).collect::<Vec<_>>(),
+                    a_base,
+                    a_stride,
+                    k,
+                    &b,
+                );
+                let gotbf = dot_product_bf16(&abf, a_base, a_stride, k, &b);
+                assert!(
+                    (gotbf - wantbf).abs() <= 5e-2 * wantbf.abs().max(1.0),
+                    "bf16 stride={a_stride} k={k}: got {gotbf} want {wantbf}"
+                );
+            }
+        }
     }
 }